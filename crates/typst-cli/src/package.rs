@@ -1,12 +1,16 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::fs::{self, File};
 use std::io::{self, Read, Write};
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
 
 use codespan_reporting::term::{self, termcolor};
 use ecow::eco_format;
 use once_cell::sync::Lazy;
-use serde::Deserialize;
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use termcolor::WriteColor;
 use typst::diag::{bail, PackageError, PackageResult, StrResult};
 use typst::syntax::package::{
@@ -21,16 +25,39 @@ const HOST: &str = "https://packages.typst.org";
 #[derive(Deserialize)]
 struct PkgMirror {
     path: String,
+    /// URL template for a sidecar checksum file (e.g.
+    /// `https://example.org/$name-$version.tar.gz.sha256`), used to verify
+    /// archives downloaded from this mirror. Unlike `preview`, where
+    /// verification against the package index is mandatory, this is
+    /// opt-in per custom mirror.
+    #[serde(default)]
+    digest_url: Option<String>,
+    /// URL template for this mirror's sparse per-package metadata
+    /// endpoint (e.g. `https://example.org/preview/meta/$name.json`).
+    /// When present, it is preferred over downloading the full index
+    /// just to look up one package's versions.
+    #[serde(default)]
+    sparse: Option<String>,
 }
 
 impl PkgMirror {
     pub fn new<S: ToString>(path: S) -> Self {
-        PkgMirror { path: path.to_string() }
+        PkgMirror { path: path.to_string(), digest_url: None, sparse: None }
     }
 
     pub fn package_download_url(&self, pkg_name: &str, pkg_version: &str) -> String {
         self.path.replace("$name", pkg_name).replace("$version", pkg_version)
     }
+
+    pub fn package_digest_url(&self, pkg_name: &str, pkg_version: &str) -> Option<String> {
+        self.digest_url
+            .as_ref()
+            .map(|url| url.replace("$name", pkg_name).replace("$version", pkg_version))
+    }
+
+    pub fn sparse_metadata_url(&self, pkg_name: &str) -> Option<String> {
+        self.sparse.as_ref().map(|url| url.replace("$name", pkg_name))
+    }
 }
 
 #[derive(Deserialize)]
@@ -41,9 +68,11 @@ impl Default for MirrorConfiguration {
         Self(
             BTreeMap::from_iter([(
                 "preview".to_string(),
-                PkgMirror::new(
-                    format!("{HOST}/preview/$name-$version.tar.gz")
-                ),
+                PkgMirror {
+                    path: format!("{HOST}/preview/$name-$version.tar.gz"),
+                    digest_url: None,
+                    sparse: Some(format!("{HOST}/preview/meta/$name.json")),
+                },
             )])
         )
     }
@@ -101,102 +130,563 @@ static PACKAGE_MIRRORS: Lazy<MirrorConfiguration> = Lazy::new(|| {
     mirrors
 });
 
+/// Number of packages downloaded concurrently by [`prepare_packages`],
+/// unless overridden via `TYPST_PACKAGE_DOWNLOAD_JOBS`.
+const DEFAULT_PARALLEL_DOWNLOADS: usize = 4;
+
+/// Serializes the per-package progress output of concurrent downloads so
+/// that lines from different worker threads don't interleave.
+static PRINT_LOCK: Mutex<()> = Mutex::new(());
+
 /// Make a package available in the on-disk cache.
 pub fn prepare_package(spec: &PackageSpec) -> PackageResult<PathBuf> {
+    prepare_packages(std::slice::from_ref(spec))
+        .remove(spec)
+        .unwrap_or_else(|| Err(PackageError::NotFound(spec.clone())))
+}
+
+/// Make a batch of packages available in the on-disk cache.
+///
+/// This is the batch counterpart to [`prepare_package`]: a document that
+/// imports a dozen `@preview` packages would otherwise pay for each
+/// download serially. Whatever is already cached is resolved up front,
+/// and the rest is collected into a shared queue that a small pool of
+/// worker threads (`DEFAULT_PARALLEL_DOWNLOADS` by default, see
+/// `TYPST_PACKAGE_DOWNLOAD_JOBS`) drains concurrently, so independent
+/// packages download in parallel instead of one at a time.
+///
+/// To actually benefit from this, whatever drives a compile needs to
+/// collect every `PackageSpec` a document imports up front and call this
+/// once per compile, instead of resolving each import through
+/// [`prepare_package`] as it's encountered.
+pub fn prepare_packages(
+    specs: &[PackageSpec],
+) -> HashMap<PackageSpec, PackageResult<PathBuf>> {
+    let mut results = HashMap::new();
+    let mut queue = VecDeque::new();
+    // A spec can legitimately appear more than once in `specs` (e.g. two
+    // imports pinned to the same version). Without deduping, two workers
+    // would race on the same `tmp_dir`/`package_dir` and could delete an
+    // archive the other is still unpacking.
+    let mut queued = HashSet::new();
+
+    for spec in specs {
+        if results.contains_key(spec) || queued.contains(spec) {
+            continue;
+        }
+        match local_package_dir(spec) {
+            Some(dir) => {
+                results.insert(spec.clone(), Ok(dir));
+            }
+            None => {
+                queued.insert(spec.clone());
+                queue.push_back(spec.clone());
+            }
+        }
+    }
+
+    if queue.is_empty() {
+        return results;
+    }
+
+    let job_count = parallel_download_jobs().min(queue.len());
+    // With a single worker there's no risk of progress output from
+    // different downloads interleaving, so that one worker gets to use
+    // the live, in-place progress meter. With more than one, concurrent
+    // redraws of that meter would garble each other, so workers fall
+    // back to a single locked start/finish line per package instead.
+    let concurrent = job_count > 1;
+    let queue = Mutex::new(queue);
+    let finished = Mutex::new(Vec::new());
+
+    thread::scope(|scope| {
+        for _ in 0..job_count {
+            scope.spawn(|| loop {
+                let spec = match queue.lock().unwrap().pop_front() {
+                    Some(spec) => spec,
+                    None => break,
+                };
+                let result = download_into_cache(&spec, concurrent);
+                finished.lock().unwrap().push((spec, result));
+            });
+        }
+    });
+
+    results.extend(finished.into_inner().unwrap());
+    results
+}
+
+/// Number of concurrent package downloads to run, configurable via the
+/// `TYPST_PACKAGE_DOWNLOAD_JOBS` environment variable.
+fn parallel_download_jobs() -> usize {
+    std::env::var("TYPST_PACKAGE_DOWNLOAD_JOBS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .filter(|&jobs| jobs > 0)
+        .unwrap_or(DEFAULT_PARALLEL_DOWNLOADS)
+}
+
+/// Locate a package that is already present in the data or cache
+/// directory, without touching the network.
+fn local_package_dir(spec: &PackageSpec) -> Option<PathBuf> {
     let subdir =
         format!("typst/packages/{}/{}/{}", spec.namespace, spec.name, spec.version);
 
     if let Some(data_dir) = dirs::data_dir() {
         let dir = data_dir.join(&subdir);
         if dir.exists() {
-            return Ok(dir);
+            return Some(dir);
         }
     }
 
     if let Some(cache_dir) = dirs::cache_dir() {
         let dir = cache_dir.join(&subdir);
-
-        // Download from network if it doesn't exist yet.
-        if PACKAGE_MIRRORS.0.contains_key(&spec.namespace.to_string()) && !dir.exists() {
-            download_package(spec, &dir)?;
+        if dir.exists() {
+            return Some(dir);
         }
+    }
 
-        if dir.exists() {
-            return Ok(dir);
+    None
+}
+
+/// Download a single missing package into the cache directory, if its
+/// namespace has a configured mirror. `concurrent` indicates whether
+/// other workers may be downloading at the same time, which decides how
+/// download progress is reported (see [`download_package`]).
+fn download_into_cache(spec: &PackageSpec, concurrent: bool) -> PackageResult<PathBuf> {
+    let cache_dir = dirs::cache_dir().ok_or_else(|| PackageError::NotFound(spec.clone()))?;
+    let subdir =
+        format!("typst/packages/{}/{}/{}", spec.namespace, spec.name, spec.version);
+    let dir = cache_dir.join(&subdir);
+
+    if PACKAGE_MIRRORS.0.contains_key(&spec.namespace.to_string()) {
+        download_package(spec, &dir, concurrent)?;
+    }
+
+    if dir.exists() {
+        Ok(dir)
+    } else {
+        Err(PackageError::NotFound(spec.clone()))
+    }
+}
+
+/// Name of the manifest file that [`mirror_packages`] keeps at the root
+/// of an export directory.
+const MIRROR_MANIFEST_NAME: &str = "mirror-manifest.json";
+
+/// Snapshot of which package/version/digest triples have already been
+/// mirrored into an export directory, so that re-running the mirror only
+/// fetches what's missing or has changed.
+#[derive(Default, Serialize, Deserialize)]
+struct MirrorManifest {
+    /// Maps `"namespace/name/version"` to the digest of the archive that
+    /// was mirrored for it.
+    packages: BTreeMap<String, String>,
+}
+
+/// A package that failed to mirror, along with why.
+pub struct MirrorFailure {
+    pub spec: PackageSpec,
+    pub error: PackageError,
+}
+
+/// Outcome of a [`mirror_packages`] run.
+#[derive(Default)]
+pub struct MirrorSummary {
+    /// Packages that were freshly downloaded into the export directory.
+    pub mirrored: Vec<PackageSpec>,
+    /// Packages that were already present with a matching digest.
+    pub skipped: Vec<PackageSpec>,
+    /// Packages that failed, collected instead of aborting the run when
+    /// `ignore_errors` is set.
+    pub failed: Vec<MirrorFailure>,
+}
+
+/// Pre-download a set of packages into a local, air-gapped package pool.
+///
+/// Unpacks each spec's archive under `export_dir`, using the same
+/// `typst/packages/{namespace}/{name}/{version}` layout that
+/// [`prepare_package`] reads from the data directory. The export holds
+/// unpacked package directories, not `.tar.gz` archives, so it cannot
+/// serve as a mirror `path` in `pkg-mirror.toml`. A manifest at the root
+/// of `export_dir` records the digest of every package already mirrored:
+/// on a re-run, packages whose directory exists and whose recorded digest
+/// still matches the expected one are skipped. A package with no expected
+/// digest (a non-`preview` mirror without a usable `digest_url`) is
+/// downloaded again on every run. When `ignore_errors` is set,
+/// a package that 404s or otherwise fails to download is recorded in the
+/// returned summary instead of aborting the remaining mirror work.
+///
+/// This is the entry point a `typst mirror` CLI subcommand should call
+/// with the specs and `--ignore-errors` flag parsed from `args.rs`; no
+/// such subcommand exists yet.
+pub fn mirror_packages(
+    specs: &[PackageSpec],
+    export_dir: &Path,
+    ignore_errors: bool,
+) -> StrResult<MirrorSummary> {
+    fs::create_dir_all(export_dir)
+        .map_err(|err| eco_format!("failed to create export directory: {err}"))?;
+
+    let manifest_path = export_dir.join(MIRROR_MANIFEST_NAME);
+    let mut manifest = read_mirror_manifest(&manifest_path).unwrap_or_default();
+    let mut summary = MirrorSummary::default();
+
+    for spec in specs {
+        let key = mirror_manifest_key(spec);
+        let subdir =
+            format!("typst/packages/{}/{}/{}", spec.namespace, spec.name, spec.version);
+        let dir = export_dir.join(&subdir);
+
+        if dir.exists() && manifest.packages.get(&key).is_some_and(|recorded| {
+            current_expected_digest(spec)
+                .ok()
+                .flatten()
+                .is_some_and(|current| current.eq_ignore_ascii_case(recorded))
+        }) {
+            summary.skipped.push(spec.clone());
+            continue;
         }
 
-        // Download from network if it doesn't exist yet.
-        if spec.namespace == "preview" {
-            download_package(spec, &dir)?;
-            if dir.exists() {
-                return Ok(dir);
+        match mirror_one_package(spec, &dir) {
+            Ok(digest) => {
+                manifest.packages.insert(key, digest);
+                summary.mirrored.push(spec.clone());
+            }
+            Err(error) if ignore_errors => {
+                summary.failed.push(MirrorFailure { spec: spec.clone(), error });
+            }
+            Err(error) => {
+                write_mirror_manifest(&manifest_path, &manifest).ok();
+                return Err(eco_format!("failed to mirror {spec}: {error}"));
             }
         }
     }
 
-    Err(PackageError::NotFound(spec.clone()))
+    write_mirror_manifest(&manifest_path, &manifest)
+        .map_err(|err| eco_format!("failed to write mirror manifest: {err}"))?;
+
+    Ok(summary)
+}
+
+/// Download a single package into `dir`, verifying it wherever an
+/// expected digest is known, and return the SHA-256 digest of its archive.
+fn mirror_one_package(spec: &PackageSpec, dir: &Path) -> PackageResult<String> {
+    if !PACKAGE_MIRRORS.0.contains_key(&spec.namespace.to_string()) {
+        return Err(PackageError::NotFound(spec.clone()));
+    }
+
+    // `mirror_packages` downloads one package at a time, so the live
+    // progress meter is safe to use here.
+    download_package(spec, dir, false)
+}
+
+/// Look up the digest a package is currently expected to match, so a
+/// mirror re-run can tell a package whose upstream digest has since
+/// changed apart from one that's genuinely still up to date.
+fn current_expected_digest(spec: &PackageSpec) -> PackageResult<Option<String>> {
+    let mirror = PACKAGE_MIRRORS
+        .0
+        .get(&spec.namespace.to_string())
+        .ok_or_else(|| PackageError::NotFound(spec.clone()))?;
+    expected_digest(spec, mirror)
+}
+
+fn mirror_manifest_key(spec: &PackageSpec) -> String {
+    format!("{}/{}/{}", spec.namespace, spec.name, spec.version)
+}
+
+fn read_mirror_manifest(path: &Path) -> Option<MirrorManifest> {
+    let content = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn write_mirror_manifest(path: &Path, manifest: &MirrorManifest) -> io::Result<()> {
+    let content = serde_json::to_string_pretty(manifest)?;
+    fs::write(path, content)
 }
 
 /// Try to determine the latest version of a package.
 pub fn determine_latest_version(
     spec: &VersionlessPackageSpec,
 ) -> StrResult<PackageVersion> {
+    available_versions(spec)?.into_iter().max().ok_or_else(|| {
+        if spec.namespace == "preview" {
+            eco_format!("failed to find package {spec}")
+        } else {
+            eco_format!("please specify the desired version")
+        }
+    })
+}
+
+/// Try to determine the version of a package matching a version
+/// fragment, e.g. so that `@preview/cetz:^0.2` resolves to the newest
+/// compatible release instead of requiring an exact pin.
+///
+/// `version_fragment` is the version part of the spec (e.g. `1.2.3`,
+/// `^0.2`, or `latest`). It's tried, in order: the `latest` tag, via
+/// [`determine_latest_version`]; an exact version, matched literally
+/// rather than as the implicit caret range `VersionReq::parse` would
+/// otherwise read it as (`^1.2.3` also matches `1.9.9`, which would
+/// silently resolve a pin to the wrong release); and finally a
+/// [`VersionReq`] range. A fragment that is none of these is an error,
+/// rather than silently falling back to `latest`.
+///
+/// Whatever resolves a `VersionlessPackageSpec` plus version fragment
+/// into a concrete `PackageSpec` needs to call this instead of
+/// [`determine_latest_version`] for the range to actually take effect.
+pub fn determine_matching_version(
+    spec: &VersionlessPackageSpec,
+    version_fragment: &str,
+) -> StrResult<PackageVersion> {
+    if version_fragment == "latest" {
+        return determine_latest_version(spec);
+    }
+
+    if let Ok(exact) = Version::parse(version_fragment) {
+        return available_versions(spec)?
+            .into_iter()
+            .find(|version| package_version_to_semver(*version) == exact)
+            .ok_or_else(|| eco_format!("package {spec} has no version {version_fragment}"));
+    }
+
+    let req = VersionReq::parse(version_fragment).map_err(|err| {
+        eco_format!("`{version_fragment}` is not a valid package version: {err}")
+    })?;
+
+    available_versions(spec)?
+        .into_iter()
+        .filter(|version| req.matches(&package_version_to_semver(*version)))
+        .max()
+        .ok_or_else(|| eco_format!("no version of {} matches {req}", spec.name))
+}
+
+/// Collect the versions of a package that are available to resolve
+/// against: the `@preview` index for that namespace, or the local data
+/// directory for any other namespace.
+fn available_versions(spec: &VersionlessPackageSpec) -> StrResult<Vec<PackageVersion>> {
     if spec.namespace == "preview" {
-        // For `@preview`, download the package index and find the latest
-        // version.
-        download_index()?
+        // Prefer the mirror's sparse per-package metadata endpoint, if it
+        // has one, so we only transfer this one package's version list
+        // instead of the whole, ever-growing index.
+        if let Some(url) = PACKAGE_MIRRORS
+            .0
+            .get("preview")
+            .and_then(|mirror| mirror.sparse_metadata_url(&spec.name))
+        {
+            return Ok(download_sparse_metadata(&spec.name, &url)?
+                .into_iter()
+                .map(|package| package.info.version)
+                .collect());
+        }
+
+        // Fall back to downloading the full package index and collecting
+        // the versions on offer.
+        Ok(download_index()?
             .iter()
-            .filter(|package| package.name == spec.name)
-            .map(|package| package.version)
-            .max()
-            .ok_or_else(|| eco_format!("failed to find package {spec}"))
+            .filter(|package| package.info.name == spec.name)
+            .map(|package| package.info.version)
+            .collect())
     } else {
         // For other namespaces, search locally. We only search in the data
         // directory and not the cache directory, because the latter is not
         // intended for storage of local packages.
         let subdir = format!("typst/packages/{}/{}", spec.namespace, spec.name);
-        dirs::data_dir()
+        Ok(dirs::data_dir()
             .into_iter()
             .flat_map(|dir| std::fs::read_dir(dir.join(&subdir)).ok())
             .flatten()
             .filter_map(|entry| entry.ok())
             .map(|entry| entry.path())
             .filter_map(|path| path.file_name()?.to_string_lossy().parse().ok())
-            .max()
-            .ok_or_else(|| eco_format!("please specify the desired version"))
+            .collect())
     }
 }
 
-/// Download a package over the network.
-fn download_package(spec: &PackageSpec, package_dir: &Path) -> PackageResult<()> {
+/// Map a [`PackageVersion`] onto a [`semver::Version`] for matching
+/// against a [`VersionReq`].
+fn package_version_to_semver(version: PackageVersion) -> Version {
+    Version::new(version.major.into(), version.minor.into(), version.patch.into())
+}
+
+/// Download a package over the network, verifying its archive digest
+/// first, and return the digest it was downloaded with.
+///
+/// `concurrent` must be set whenever other packages may be downloading
+/// on other threads at the same time. [`download_with_progress`] renders
+/// a single live, in-place progress meter, which several threads writing
+/// to at once would garble; when `concurrent` is set we fetch quietly
+/// instead and report progress as one locked start/finish line per
+/// package, so concurrent workers never interleave terminal output.
+fn download_package(
+    spec: &PackageSpec,
+    package_dir: &Path,
+    concurrent: bool,
+) -> PackageResult<String> {
     let namespace = spec.namespace.to_string();
 
     assert!(PACKAGE_MIRRORS.0.contains_key(&namespace));
 
-    let url = PACKAGE_MIRRORS.0
-        .get(&namespace)
-        .unwrap()
-        .package_download_url(&spec.name, &spec.version.to_string());
+    let mirror = PACKAGE_MIRRORS.0.get(&namespace).unwrap();
+    let url = mirror.package_download_url(&spec.name, &spec.version.to_string());
 
-    print_downloading(spec).unwrap();
+    {
+        // Hold the lock only long enough to print, so concurrent workers
+        // don't garble each other's progress lines.
+        let _guard = PRINT_LOCK.lock().unwrap();
+        print_downloading(spec).unwrap();
+    }
 
-    let data = match download_with_progress(&url) {
-        Ok(data) => data,
-        Err(ureq::Error::Status(404, _)) => {
-            return Err(PackageError::NotFound(spec.clone()))
+    let data = if concurrent {
+        match download(&url) {
+            Ok(response) => {
+                let mut buf = Vec::new();
+                response.into_reader().read_to_end(&mut buf).map_err(|err| {
+                    PackageError::NetworkFailed(Some(eco_format!("{err}")))
+                })?;
+                buf
+            }
+            Err(ureq::Error::Status(404, _)) => {
+                return Err(PackageError::NotFound(spec.clone()))
+            }
+            Err(err) => {
+                return Err(PackageError::NetworkFailed(Some(eco_format!("{err}"))))
+            }
+        }
+    } else {
+        match download_with_progress(&url) {
+            Ok(data) => data,
+            Err(ureq::Error::Status(404, _)) => {
+                return Err(PackageError::NotFound(spec.clone()))
+            }
+            Err(err) => {
+                return Err(PackageError::NetworkFailed(Some(eco_format!("{err}"))))
+            }
         }
-        Err(err) => return Err(PackageError::NetworkFailed(Some(eco_format!("{err}")))),
     };
 
-    let decompressed = flate2::read::GzDecoder::new(data.as_slice());
-    tar::Archive::new(decompressed).unpack(package_dir).map_err(|err| {
-        fs::remove_dir_all(package_dir).ok();
+    if concurrent {
+        let _guard = PRINT_LOCK.lock().unwrap();
+        print_downloaded(spec).unwrap();
+    }
+
+    let mut hasher = Sha256::new();
+    hasher.update(&data);
+    let digest = format!("{:x}", hasher.finalize());
+
+    if let Some(expected) = expected_digest(spec, mirror)? {
+        if !digest.eq_ignore_ascii_case(&expected) {
+            return Err(PackageError::MalformedArchive(Some(eco_format!(
+                "checksum mismatch for {spec}: expected {expected}, got {digest}"
+            ))));
+        }
+    }
+
+    install_archive(&data, package_dir)?;
+
+    Ok(digest)
+}
+
+/// Unpack a downloaded archive into `package_dir`, replacing whatever (if
+/// anything) is there already.
+///
+/// Unpacks into a private temp directory next to the final location and
+/// only renames it into place once fully written, so that a failed or
+/// interrupted download never leaves a half-written package directory
+/// behind for a concurrent worker (or a later run) to trip over.
+/// `package_dir` is removed first if it already exists: `fs::rename`
+/// refuses to replace a non-empty directory, which a re-mirror of a
+/// package that's already on disk (e.g. because its upstream digest
+/// changed) would otherwise hit.
+fn install_archive(data: &[u8], package_dir: &Path) -> PackageResult<()> {
+    let tmp_dir = package_dir.with_file_name(format!(
+        ".{}-download",
+        package_dir.file_name().unwrap_or_default().to_string_lossy()
+    ));
+    fs::remove_dir_all(&tmp_dir).ok();
+
+    let decompressed = flate2::read::GzDecoder::new(data);
+    tar::Archive::new(decompressed).unpack(&tmp_dir).map_err(|err| {
+        fs::remove_dir_all(&tmp_dir).ok();
+        PackageError::MalformedArchive(Some(eco_format!("{err}")))
+    })?;
+
+    if package_dir.exists() {
+        fs::remove_dir_all(package_dir).map_err(|err| {
+            fs::remove_dir_all(&tmp_dir).ok();
+            PackageError::MalformedArchive(Some(eco_format!("{err}")))
+        })?;
+    }
+
+    fs::rename(&tmp_dir, package_dir).map_err(|err| {
+        fs::remove_dir_all(&tmp_dir).ok();
         PackageError::MalformedArchive(Some(eco_format!("{err}")))
     })
 }
 
+/// The digest an archive is expected to match before it is trusted,
+/// looked up from the `@preview` index or, for other namespaces, from a
+/// mirror's sidecar checksum file. Returns `None` when the mirror has no
+/// `digest_url`, or when its sidecar checksum file is not found (404);
+/// neither is possible for `preview`, where verification is mandatory.
+fn expected_digest(
+    spec: &PackageSpec,
+    mirror: &PkgMirror,
+) -> PackageResult<Option<String>> {
+    if spec.namespace == "preview" {
+        // Prefer the mirror's sparse per-package metadata endpoint, if it
+        // has one, and its on-disk cache, rather than re-fetching the
+        // whole, ever-growing index just to look up one package's
+        // digest. This matters in particular during `prepare_packages`,
+        // where up to `TYPST_PACKAGE_DOWNLOAD_JOBS` downloads call this
+        // concurrently.
+        let packages = if let Some(url) = mirror.sparse_metadata_url(&spec.name) {
+            download_sparse_metadata(&spec.name, &url)
+                .map_err(|err| PackageError::NetworkFailed(Some(eco_format!("{err}"))))?
+        } else {
+            download_index()
+                .map_err(|err| PackageError::NetworkFailed(Some(eco_format!("{err}"))))?
+        };
+
+        let digest = packages
+            .into_iter()
+            .find(|package| package.info.name == spec.name && package.info.version == spec.version)
+            .and_then(|package| package.sha256)
+            .ok_or_else(|| PackageError::NotFound(spec.clone()))?;
+        return Ok(Some(digest));
+    }
+
+    let Some(digest_url) =
+        mirror.package_digest_url(&spec.name, &spec.version.to_string())
+    else {
+        return Ok(None);
+    };
+
+    match download(&digest_url) {
+        Ok(response) => {
+            let text = response
+                .into_string()
+                .map_err(|err| PackageError::NetworkFailed(Some(eco_format!("{err}"))))?;
+            let digest = text.split_whitespace().next().unwrap_or_default();
+            Ok(Some(digest.to_lowercase()))
+        }
+        Err(ureq::Error::Status(404, _)) => Ok(None),
+        Err(err) => Err(PackageError::NetworkFailed(Some(eco_format!("{err}")))),
+    }
+}
+
+/// One entry of the `@preview` package index, carrying the digest that
+/// archives are verified against before being trusted.
+#[derive(Deserialize)]
+struct IndexedPackage {
+    #[serde(flatten)]
+    info: PackageInfo,
+    /// Hex-encoded SHA-256 digest of the package's `.tar.gz` archive.
+    sha256: Option<String>,
+}
+
 /// Download the `@preview` package index.
-fn download_index() -> StrResult<Vec<PackageInfo>> {
+fn download_index() -> StrResult<Vec<IndexedPackage>> {
     let url = format!("{HOST}/preview/index.json");
     match download(&url) {
         Ok(response) => response
@@ -209,6 +699,116 @@ fn download_index() -> StrResult<Vec<PackageInfo>> {
     }
 }
 
+/// A cached sparse metadata response, together with the HTTP validators
+/// needed to issue a conditional request for it next time.
+#[derive(Default, Serialize, Deserialize)]
+struct SparseMetadataCache {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    body: String,
+}
+
+/// Fetch a mirror's sparse per-package metadata, i.e. just the version
+/// list for one package rather than the whole index. Honors HTTP caching
+/// headers: a prior response's `ETag`/`Last-Modified` are persisted
+/// alongside the body under the cache directory and replayed as
+/// `If-None-Match`/`If-Modified-Since`, so a mirror that hasn't changed
+/// the package can answer with `304 Not Modified` and skip re-sending
+/// the body.
+fn download_sparse_metadata(
+    pkg_name: &str,
+    url: &str,
+) -> StrResult<Vec<IndexedPackage>> {
+    let cache_path = sparse_metadata_cache_path(pkg_name);
+    let cached = cache_path.as_deref().and_then(read_sparse_metadata_cache);
+
+    let mut request = ureq::get(url);
+    if let Some(cached) = &cached {
+        if let Some(etag) = &cached.etag {
+            request = request.set("If-None-Match", etag);
+        }
+        if let Some(last_modified) = &cached.last_modified {
+            request = request.set("If-Modified-Since", last_modified);
+        }
+    }
+
+    let outcome = match request.call() {
+        Ok(response) if response.status() == 304 => SparseFetchOutcome::NotModified,
+        Ok(response) => SparseFetchOutcome::Fresh {
+            etag: response.header("ETag").map(str::to_string),
+            last_modified: response.header("Last-Modified").map(str::to_string),
+            body: response.into_string().map_err(|err| {
+                eco_format!("failed to read package metadata for {pkg_name}: {err}")
+            })?,
+        },
+        Err(ureq::Error::Status(404, _)) => {
+            bail!("failed to fetch package metadata for {pkg_name} (not found)")
+        }
+        Err(err) => bail!("failed to fetch package metadata for {pkg_name} ({err})"),
+    };
+
+    let body = resolve_sparse_body(pkg_name, outcome, cached, cache_path.as_deref())?;
+
+    serde_json::from_str(&body)
+        .map_err(|err| eco_format!("failed to parse package metadata for {pkg_name}: {err}"))
+}
+
+/// What a sparse-metadata request returned: either a fresh body (with the
+/// validators to cache alongside it), or confirmation that the cached
+/// body is still current.
+enum SparseFetchOutcome {
+    Fresh { etag: Option<String>, last_modified: Option<String>, body: String },
+    NotModified,
+}
+
+/// Turn a [`SparseFetchOutcome`] plus whatever was cached before the
+/// request into the metadata body to parse, persisting a fresh response
+/// to `cache_path` along the way. Pulled out of [`download_sparse_metadata`]
+/// so the cache-hit/cache-miss/not-modified branching can be tested
+/// without making a real HTTP request.
+fn resolve_sparse_body(
+    pkg_name: &str,
+    outcome: SparseFetchOutcome,
+    cached: Option<SparseMetadataCache>,
+    cache_path: Option<&Path>,
+) -> StrResult<String> {
+    match outcome {
+        SparseFetchOutcome::Fresh { etag, last_modified, body } => {
+            if let Some(cache_path) = cache_path {
+                let cache = SparseMetadataCache { etag, last_modified, body: body.clone() };
+                write_sparse_metadata_cache(cache_path, &cache);
+            }
+            Ok(body)
+        }
+        SparseFetchOutcome::NotModified => cached.map(|cache| cache.body).ok_or_else(|| {
+            eco_format!(
+                "mirror returned 304 Not Modified for {pkg_name}, but nothing is cached"
+            )
+        }),
+    }
+}
+
+/// Where the cached sparse metadata response and its HTTP validators are
+/// persisted for a given package name.
+fn sparse_metadata_cache_path(pkg_name: &str) -> Option<PathBuf> {
+    let cache_dir = dirs::cache_dir()?;
+    Some(cache_dir.join("typst/packages-sparse-meta").join(format!("{pkg_name}.json")))
+}
+
+fn read_sparse_metadata_cache(path: &Path) -> Option<SparseMetadataCache> {
+    let content = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn write_sparse_metadata_cache(path: &Path, cache: &SparseMetadataCache) {
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    if let Ok(content) = serde_json::to_string(cache) {
+        let _ = fs::write(path, content);
+    }
+}
+
 /// Print that a package downloading is happening.
 fn print_downloading(spec: &PackageSpec) -> io::Result<()> {
     let styles = term::Styles::default();
@@ -220,3 +820,233 @@ fn print_downloading(spec: &PackageSpec) -> io::Result<()> {
     out.reset()?;
     writeln!(out, " {spec}")
 }
+
+/// Print that a package finished downloading. Used in place of a live
+/// progress meter when multiple packages are downloading concurrently.
+fn print_downloaded(spec: &PackageSpec) -> io::Result<()> {
+    let styles = term::Styles::default();
+
+    let mut out = terminal::out();
+    out.set_color(&styles.header_note)?;
+    write!(out, "downloaded")?;
+
+    out.reset()?;
+    writeln!(out, " {spec}")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+
+    /// A process-unique scratch directory under the system temp dir,
+    /// removed when the returned guard is dropped.
+    struct ScratchDir(PathBuf);
+
+    impl ScratchDir {
+        fn new(label: &str) -> Self {
+            static COUNTER: AtomicU32 = AtomicU32::new(0);
+            let id = COUNTER.fetch_add(1, Ordering::Relaxed);
+            let dir = std::env::temp_dir()
+                .join(format!("typst-package-test-{label}-{}-{id}", std::process::id()));
+            fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+    }
+
+    impl Drop for ScratchDir {
+        fn drop(&mut self) {
+            fs::remove_dir_all(&self.0).ok();
+        }
+    }
+
+    /// Builds a minimal single-file `.tar.gz` archive in memory, for
+    /// feeding to [`install_archive`] without touching the network.
+    fn tar_gz_with_one_file(name: &str, contents: &[u8]) -> Vec<u8> {
+        let mut tar_bytes = Vec::new();
+        {
+            let mut builder = tar::Builder::new(&mut tar_bytes);
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, name, contents).unwrap();
+            builder.finish().unwrap();
+        }
+
+        let mut gz_bytes = Vec::new();
+        {
+            let mut encoder = flate2::write::GzEncoder::new(
+                &mut gz_bytes,
+                flate2::Compression::default(),
+            );
+            encoder.write_all(&tar_bytes).unwrap();
+            encoder.finish().unwrap();
+        }
+        gz_bytes
+    }
+
+    #[test]
+    fn install_archive_replaces_an_already_existing_package_directory() {
+        let scratch = ScratchDir::new("install-archive");
+        let package_dir = scratch.0.join("preview/cetz/0.2.0");
+
+        // Simulate a package that's already on disk from a previous
+        // mirror or download, with a digest that's since changed.
+        fs::create_dir_all(&package_dir).unwrap();
+        fs::write(package_dir.join("lib.typ"), b"old").unwrap();
+
+        let data = tar_gz_with_one_file("lib.typ", b"new");
+        install_archive(&data, &package_dir).unwrap();
+
+        assert_eq!(fs::read(package_dir.join("lib.typ")).unwrap(), b"new");
+    }
+
+    #[test]
+    fn package_version_to_semver_maps_components_in_order() {
+        let version = PackageVersion { major: 1, minor: 2, patch: 3 };
+        assert_eq!(package_version_to_semver(version), Version::new(1, 2, 3));
+    }
+
+    #[test]
+    fn version_fragment_parses_as_a_range_when_possible() {
+        // Range-like fragments should parse as a `VersionReq`...
+        assert!(VersionReq::parse("^0.2").is_ok());
+        assert!(VersionReq::parse(">=0.3, <0.5").is_ok());
+        // ...and so does a bare exact version, since semver reads it as
+        // an implicit caret range (`^1.2.3` also matches `1.9.9`) —
+        // `determine_matching_version` must check for an exact version
+        // first and never let this ambiguity reach `VersionReq::matches`.
+        assert!(VersionReq::parse("1.2.3").is_ok());
+        assert!(Version::parse("1.2.3").is_ok());
+        // Garbage is neither, and should be a hard error rather than a
+        // silent fall-through to "latest".
+        assert!(VersionReq::parse("not-a-version").is_err());
+        assert!(Version::parse("not-a-version").is_err());
+    }
+
+    #[test]
+    fn matching_version_picks_the_highest_satisfying_candidate() {
+        let req = VersionReq::parse("^0.2").unwrap();
+        let candidates = [
+            PackageVersion { major: 0, minor: 1, patch: 9 },
+            PackageVersion { major: 0, minor: 2, patch: 0 },
+            PackageVersion { major: 0, minor: 2, patch: 5 },
+            PackageVersion { major: 0, minor: 3, patch: 0 },
+        ];
+
+        let best = candidates
+            .into_iter()
+            .filter(|version| req.matches(&package_version_to_semver(*version)))
+            .max();
+
+        assert_eq!(best, Some(PackageVersion { major: 0, minor: 2, patch: 5 }));
+    }
+
+    #[test]
+    fn exact_version_fragment_matches_only_that_version_not_the_newest_compatible_one() {
+        let exact = Version::parse("1.2.3").unwrap();
+        let candidates = [
+            PackageVersion { major: 1, minor: 2, patch: 3 },
+            PackageVersion { major: 1, minor: 9, patch: 9 },
+        ];
+
+        // A real `VersionReq` range would pick 1.9.9 here (`^1.2.3`
+        // matches it), but an exact pin must resolve to exactly 1.2.3.
+        let matched = candidates
+            .into_iter()
+            .find(|version| package_version_to_semver(*version) == exact);
+
+        assert_eq!(matched, Some(PackageVersion { major: 1, minor: 2, patch: 3 }));
+    }
+
+    #[test]
+    fn resolve_sparse_body_caches_a_fresh_response() {
+        let scratch = ScratchDir::new("sparse-fresh");
+        let cache_path = scratch.0.join("cetz.json");
+
+        let outcome = SparseFetchOutcome::Fresh {
+            etag: Some("\"abc\"".into()),
+            last_modified: None,
+            body: "[]".into(),
+        };
+        let body =
+            resolve_sparse_body("cetz", outcome, None, Some(&cache_path)).unwrap();
+
+        assert_eq!(body, "[]");
+        let cached = read_sparse_metadata_cache(&cache_path).unwrap();
+        assert_eq!(cached.etag.as_deref(), Some("\"abc\""));
+        assert_eq!(cached.body, "[]");
+    }
+
+    #[test]
+    fn resolve_sparse_body_reuses_the_cache_on_not_modified() {
+        let cached = SparseMetadataCache {
+            etag: Some("\"abc\"".into()),
+            last_modified: None,
+            body: "[{\"cached\":true}]".into(),
+        };
+
+        let body =
+            resolve_sparse_body("cetz", SparseFetchOutcome::NotModified, Some(cached), None)
+                .unwrap();
+
+        assert_eq!(body, "[{\"cached\":true}]");
+    }
+
+    #[test]
+    fn resolve_sparse_body_errors_on_not_modified_without_a_cache() {
+        let result = resolve_sparse_body("cetz", SparseFetchOutcome::NotModified, None, None);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn sparse_metadata_cache_round_trips_through_disk() {
+        let scratch = ScratchDir::new("sparse-roundtrip");
+        let path = scratch.0.join("mypkg.json");
+
+        let cache = SparseMetadataCache {
+            etag: Some("\"v1\"".into()),
+            last_modified: Some("Tue, 01 Jul 2025 00:00:00 GMT".into()),
+            body: "[{\"version\":\"0.1.0\"}]".into(),
+        };
+        write_sparse_metadata_cache(&path, &cache);
+
+        let read_back = read_sparse_metadata_cache(&path).unwrap();
+        assert_eq!(read_back.etag, cache.etag);
+        assert_eq!(read_back.last_modified, cache.last_modified);
+        assert_eq!(read_back.body, cache.body);
+    }
+
+    #[test]
+    fn mirror_manifest_round_trips_through_disk() {
+        let scratch = ScratchDir::new("mirror-manifest");
+        let path = scratch.0.join(MIRROR_MANIFEST_NAME);
+
+        let mut manifest = MirrorManifest::default();
+        manifest.packages.insert("preview/cetz/0.2.0".into(), "deadbeef".into());
+        write_mirror_manifest(&path, &manifest).unwrap();
+
+        let read_back = read_mirror_manifest(&path).unwrap();
+        assert_eq!(read_back.packages.get("preview/cetz/0.2.0").map(String::as_str), Some("deadbeef"));
+
+        // A directory with no manifest yet is simply "nothing cached", not
+        // an error.
+        assert!(read_mirror_manifest(&scratch.0.join("missing.json")).is_none());
+    }
+
+    #[test]
+    fn parallel_download_jobs_falls_back_on_an_invalid_override() {
+        std::env::remove_var("TYPST_PACKAGE_DOWNLOAD_JOBS");
+        assert_eq!(parallel_download_jobs(), DEFAULT_PARALLEL_DOWNLOADS);
+
+        std::env::set_var("TYPST_PACKAGE_DOWNLOAD_JOBS", "0");
+        assert_eq!(parallel_download_jobs(), DEFAULT_PARALLEL_DOWNLOADS);
+
+        std::env::set_var("TYPST_PACKAGE_DOWNLOAD_JOBS", "8");
+        assert_eq!(parallel_download_jobs(), 8);
+
+        std::env::remove_var("TYPST_PACKAGE_DOWNLOAD_JOBS");
+    }
+}